@@ -1,23 +1,37 @@
 // AXUM WEB FRAMEWORK IMPORTS
 // Axum is a modern, ergonomic web framework for Rust built on top of tokio and hyper
 use axum::{
-    extract::{Path, Query, State}, // Extractors to get data from HTTP requests
+    extract::{Path, Query, Request, State}, // Extractors to get data from HTTP requests
     http::StatusCode,              // HTTP status codes (200, 404, 500, etc.)
-    response::Json,                // JSON response wrapper
-    routing::{get, post, put, delete}, // HTTP method routing functions
+    middleware::{self, Next},      // Middleware layer for the API-key check
+    response::{
+        sse::{Event, Sse},        // Server-sent events, for pushing geofence transitions
+        IntoResponse, Json, Response,
+    },
+    routing::{get, post, put, patch, delete}, // HTTP method routing functions
     Router,                        // Main router to define API endpoints
 };
+use async_trait::async_trait;         // Lets the GeocoderClient trait expose an async fn as a trait object
+use futures_util::stream::{Stream, StreamExt}; // The SSE handler returns a Stream of events
 use serde::{Deserialize, Serialize}; // For JSON serialization/deserialization
-use std::collections::HashMap;        // In-memory storage (replace with database later)
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions}; // Durable SQLite storage + connection pool
+use sqlx::{QueryBuilder, Sqlite}; // Builds the WHERE clause for get_todos' optional filters
+use std::convert::Infallible;
+use std::str::FromStr; // For SqliteConnectOptions::from_str
+use std::collections::{HashMap, HashSet}; // HashMap for caches/indexes, HashSet for dedup
 use std::sync::{Arc, Mutex};         // Thread-safe shared state
+use tokio::sync::broadcast;           // Fans geofence transitions out to any number of SSE subscribers
+use tokio_stream::wrappers::BroadcastStream; // Adapts a broadcast::Receiver into a Stream
 use tower_http::cors::CorsLayer;     // Cross-Origin Resource Sharing for web browsers
+use utoipa::{IntoParams, OpenApi, ToSchema}; // Generates the OpenAPI spec from annotated types/handlers
+use utoipa_swagger_ui::SwaggerUi;     // Serves Swagger UI from the generated spec
 
 // CORE DATA STRUCTURES
 // These are your existing structs with Serde traits added for JSON conversion
 
 /// Todo represents a task that needs to be completed
 /// Serde traits allow automatic conversion to/from JSON for API responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Todo {
     pub id: u32,                                    // Unique identifier - consider using UUID for production
     pub title: String,                              // Human-readable task name
@@ -31,7 +45,7 @@ pub struct Todo {
 
 /// LocationTrigger defines a geographic area that can trigger notifications
 /// When a user enters this area, the associated Todo becomes "active"
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LocationTrigger {
     pub id: u32,          // Unique identifier for this trigger
     pub name: String,     // Human-readable name (e.g., "Home", "Office", "Grocery Store")
@@ -45,15 +59,52 @@ pub struct LocationTrigger {
 
 /// LocationQuery represents GPS coordinates sent from the iOS app
 /// Used in the /todos/nearby endpoint to find location-relevant todos
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
 pub struct LocationQuery {
-    lat: f64,  // Current latitude from iOS device
-    lng: f64,  // Current longitude from iOS device
+    lat: f64,            // Current latitude from iOS device
+    lng: f64,            // Current longitude from iOS device
+    device_id: String,   // Identifies this device so geofence state can be tracked per-device
+}
+
+/// ListOptions are the optional pagination/filter query params accepted by `GET /todos`
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
+pub struct ListOptions {
+    pub limit: Option<i64>,                    // Page size, defaults to 50, capped at 200
+    pub offset: Option<i64>,                   // Rows to skip, defaults to 0
+    pub completed: Option<bool>,               // Filter by completion status
+    pub due_before: Option<String>,            // Filter to todos due on/before this ISO date string
+    pub min_completion_percentage: Option<u8>, // Filter to todos at least this far along
+}
+
+/// TodoPage wraps a page of todos with the total count so the client can drive infinite scroll
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TodoPage {
+    pub total: i64,
+    pub todos: Vec<Todo>,
+}
+
+/// SearchQuery is the `q` query param accepted by `GET /todos/search`
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
+pub struct SearchQuery {
+    pub q: String, // Matched against title, description, and personal_notes
+}
+
+/// MarkDone is the payload for `PATCH /todos/{id}/done`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkDone {
+    #[serde(default = "MarkDone::default_completed")]
+    pub completed: bool, // Defaults to true: PATCHing /done with an empty JSON object `{}` marks it complete
+}
+
+impl MarkDone {
+    fn default_completed() -> bool {
+        true
+    }
 }
 
 /// CreateTodo represents the payload when creating a new todo
 /// Similar to Todo but without ID (server generates it) and defaults completed to false
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateTodo {
     pub title: String,                              // Required: task name
     pub description: Option<String>,                // Optional: detailed description
@@ -61,31 +112,249 @@ pub struct CreateTodo {
     pub personal_notes: Option<String>,             // Optional: user notes
     pub completion_percentage: Option<u8>,          // Optional: initial progress
     pub location_triggers: Option<Vec<LocationTrigger>>, // Optional: geographic triggers
+    pub address: Option<String>,                    // Optional: free-text address, geocoded into a LocationTrigger
+}
+
+// GEOCODING
+// Turns the free-text addresses the iOS app's user types ("123 Main St, Springfield")
+// into the `latitude`/`longitude` pair a `LocationTrigger` actually needs.
+
+/// GeocodeRequest is the payload for `POST /geocode`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GeocodeRequest {
+    pub address: String, // Free-text street address, US or worldwide
 }
 
+/// GeocodeResponse mirrors the resolved coordinates for a `GeocodeRequest`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GeocodeResponse {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// GeocodeError covers the ways an address lookup can fail
+#[derive(Debug)]
+pub enum GeocodeError {
+    NotFound,    // The geocoding service had no match for this address
+    ServiceError(String), // The geocoding service itself failed (network, bad response, etc.)
+}
+
+/// GeocoderClient abstracts over "turn an address into coordinates" so the backing
+/// service (Nominatim, Google, Mapbox, ...) can be swapped without touching handlers.
+#[async_trait]
+pub trait GeocoderClient: Send + Sync {
+    async fn geocode(&self, address: &str) -> Result<GeocodeResponse, GeocodeError>;
+}
+
+/// NominatimGeocoder calls the free OpenStreetMap Nominatim API via reqwest.
+/// No API key required, which keeps local dev and the iOS app's happy path simple.
+///
+/// Nominatim's usage policy caps free-tier traffic at 1 request/second per client and will
+/// UA-block callers that exceed it - `resolve_address`'s cache keeps repeat lookups of the same
+/// address from counting against that, but a real burst of distinct uncached addresses would
+/// still need a request queue or rate limiter in front of this client, which doesn't exist yet.
+pub struct NominatimGeocoder {
+    client: reqwest::Client,
+}
+
+/// Nominatim isn't always fast, but a hung request shouldn't be able to hold a `create_todo`/
+/// `geocode` call open indefinitely.
+const GEOCODE_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+impl NominatimGeocoder {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(GEOCODE_REQUEST_TIMEOUT)
+                .build()
+                .expect("failed to build geocoder HTTP client"),
+        }
+    }
+}
+
+/// Shape of the JSON array Nominatim's `/search` endpoint returns
+#[derive(Debug, Deserialize)]
+struct NominatimResult {
+    lat: String,
+    lon: String,
+}
+
+#[async_trait]
+impl GeocoderClient for NominatimGeocoder {
+    async fn geocode(&self, address: &str) -> Result<GeocodeResponse, GeocodeError> {
+        let response = self
+            .client
+            .get("https://nominatim.openstreetmap.org/search")
+            .query(&[("q", address), ("format", "json"), ("limit", "1")])
+            .header("User-Agent", "fuct-todo-server/0.1") // Nominatim requires a descriptive User-Agent
+            .send()
+            .await
+            .map_err(|e| GeocodeError::ServiceError(e.to_string()))?;
+
+        let results: Vec<NominatimResult> = response
+            .json()
+            .await
+            .map_err(|e| GeocodeError::ServiceError(e.to_string()))?;
+
+        let first = results.into_iter().next().ok_or(GeocodeError::NotFound)?;
+
+        let latitude: f64 = first
+            .lat
+            .parse()
+            .map_err(|_| GeocodeError::ServiceError("non-numeric latitude from geocoder".into()))?;
+        let longitude: f64 = first
+            .lon
+            .parse()
+            .map_err(|_| GeocodeError::ServiceError("non-numeric longitude from geocoder".into()))?;
+
+        Ok(GeocodeResponse { latitude, longitude })
+    }
+}
+
+/// Normalizes an address string so "123 Main St" and "123   main st" hit the same cache entry
+fn normalize_address(address: &str) -> String {
+    address.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// GeocodeCache maps a normalized address string to its resolved coordinates so repeated
+/// lookups (e.g. re-geocoding the same home address across todos) don't re-hit the API.
+type GeocodeCache = Arc<Mutex<HashMap<String, GeocodeResponse>>>;
+
 // SHARED APPLICATION STATE
-// Arc<Mutex<HashMap>> provides thread-safe shared access to todo storage
-// Arc = Atomic Reference Counter (multiple owners)
-// Mutex = Mutual Exclusion (thread-safe access)
-// HashMap = Key-value storage (todo_id -> Todo)
-type TodoStore = Arc<Mutex<HashMap<u32, Todo>>>;
+// The todo store used to be an Arc<Mutex<HashMap>>, which lost everything on restart and
+// serialized every request behind one lock. It's now a `sqlx` connection pool over SQLite,
+// so persistence survives restarts and reads/writes run concurrently against the pool.
+
+/// AppState bundles everything a handler might need: the database pool, a geocoder
+/// client to resolve addresses, and a cache so the same address isn't looked up twice.
+#[derive(Clone)]
+struct AppState {
+    pool: SqlitePool,
+    geocoder: Arc<dyn GeocoderClient>,
+    geocode_cache: GeocodeCache,
+    api_key: Option<Arc<String>>, // None disables auth (local dev); Some(_) is the required x-api-key value
+    spatial_index: SharedSpatialIndex, // Geohash index over every trigger's position, see below
+    device_geofence_state: DeviceGeofenceState, // Per-device "currently inside" trigger sets
+    geofence_events: broadcast::Sender<GeofenceEvent>, // Fans transitions out to SSE subscribers
+}
+
+// DATABASE LAYER
+// Row types mirror the `todos` and `location_triggers` tables (see migrations/0001_init.sql)
+// and get assembled into the API-facing `Todo`/`LocationTrigger` structs below. IDs are no
+// longer generated in the application: SQLite's `INTEGER PRIMARY KEY` autoincrements them,
+// which is the thread-safe behavior the old `static mut NEXT_ID` counter was trying to fake.
 
-// SIMPLE ID GENERATION
-// In production, use UUID or database auto-increment
-// This is unsafe but simple for demonstration
-static mut NEXT_ID: u32 = 1;
+#[derive(Debug, sqlx::FromRow)]
+struct TodoRow {
+    id: i64,
+    title: String,
+    completed: bool,
+    description: Option<String>,
+    due_date: Option<String>,
+    personal_notes: Option<String>,
+    completion_percentage: Option<i64>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct LocationTriggerRow {
+    id: i64,
+    todo_id: i64,
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    radius: f64,
+}
 
-/// Generates the next available ID for new todos
-/// WARNING: This is not thread-safe or production-ready
-/// Consider using AtomicU32 or UUID instead
-fn get_next_id() -> u32 {
-    unsafe {
-        let id = NEXT_ID;
-        NEXT_ID += 1;
-        id
+impl From<LocationTriggerRow> for LocationTrigger {
+    fn from(row: LocationTriggerRow) -> Self {
+        LocationTrigger {
+            id: row.id as u32,
+            name: row.name,
+            latitude: row.latitude,
+            longitude: row.longitude,
+            radius: row.radius,
+        }
     }
 }
 
+impl TodoRow {
+    /// Combines this row with its already-fetched triggers into the API-facing `Todo`
+    fn into_todo(self, triggers: Vec<LocationTrigger>) -> Todo {
+        Todo {
+            id: self.id as u32,
+            title: self.title,
+            completed: self.completed,
+            description: self.description,
+            due_date: self.due_date,
+            personal_notes: self.personal_notes,
+            completion_percentage: self.completion_percentage.map(|p| p as u8),
+            location_triggers: if triggers.is_empty() { None } else { Some(triggers) },
+        }
+    }
+}
+
+/// Fetches the location triggers belonging to a single todo, ordered by insertion
+async fn fetch_triggers(pool: &SqlitePool, todo_id: i64) -> Result<Vec<LocationTrigger>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, LocationTriggerRow>(
+        "SELECT id, todo_id, name, latitude, longitude, radius FROM location_triggers WHERE todo_id = ? ORDER BY id",
+    )
+    .bind(todo_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(LocationTrigger::from).collect())
+}
+
+/// Fetches the location triggers belonging to the given todo IDs, grouped by todo ID. Used by
+/// handlers that need triggers for a whole batch of todos at once (e.g. a page of `/todos` or a
+/// `/todos/search` match set) so they issue one query for the batch instead of one per todo -
+/// scoped to that batch rather than every trigger in the system, so it stays as bounded as the
+/// `todos` query that produced the IDs.
+async fn fetch_triggers_grouped(pool: &SqlitePool, todo_ids: &[i64]) -> Result<HashMap<i64, Vec<LocationTrigger>>, sqlx::Error> {
+    if todo_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT id, todo_id, name, latitude, longitude, radius FROM location_triggers WHERE todo_id IN (",
+    );
+    let mut separated = query.separated(", ");
+    for todo_id in todo_ids {
+        separated.push_bind(todo_id);
+    }
+    query.push(") ORDER BY id");
+
+    let rows: Vec<LocationTriggerRow> = query.build_query_as().fetch_all(pool).await?;
+
+    let mut grouped: HashMap<i64, Vec<LocationTrigger>> = HashMap::new();
+    for row in rows {
+        grouped.entry(row.todo_id).or_default().push(row.into());
+    }
+    Ok(grouped)
+}
+
+/// Inserts a location trigger for a given todo and returns it with its assigned ID
+async fn insert_trigger(
+    pool: &SqlitePool,
+    todo_id: i64,
+    trigger: &LocationTrigger,
+) -> Result<LocationTrigger, sqlx::Error> {
+    let row: LocationTriggerRow = sqlx::query_as(
+        "INSERT INTO location_triggers (todo_id, name, latitude, longitude, radius)
+         VALUES (?, ?, ?, ?, ?)
+         RETURNING id, todo_id, name, latitude, longitude, radius",
+    )
+    .bind(todo_id)
+    .bind(&trigger.name)
+    .bind(trigger.latitude)
+    .bind(trigger.longitude)
+    .bind(trigger.radius)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.into())
+}
+
 // GEOSPATIAL CALCULATIONS
 
 /// Calculates the distance between two GPS coordinates using the Haversine formula
@@ -114,176 +383,973 @@ fn calculate_distance(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
     EARTH_RADIUS_METERS * c
 }
 
+// GEOHASH SPATIAL INDEX
+// `get_nearby_todos` used to run `calculate_distance` against every trigger of every todo on
+// every GPS update (O(todos x triggers) per request). A geohash buckets nearby points into the
+// same short string, so a query only needs to check the handful of triggers sharing (or
+// bordering) the query point's cell instead of the whole table.
+
+const GEOHASH_BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+/// Precision 6 gives ~1.2km x 0.61km cells, comfortably bigger than a typical geofence radius
+/// (tens to a few hundred meters) while still keeping each cell's trigger list small.
+const GEOHASH_PRECISION: usize = 6;
+/// Rough meters-per-degree of latitude, used only to size the neighbor ring - doesn't need to
+/// be exact, just close enough that "how many cells wide is this radius" is a sane estimate.
+const METERS_PER_LAT_DEGREE: f64 = 111_320.0;
+/// Triggers with a radius at or below this are indexed by geohash cell and found by widening a
+/// ring sized to exactly this distance - since no indexed trigger's radius can exceed it, that
+/// ring is guaranteed to reach every cell an in-range trigger could be sitting in. Triggers
+/// above it (rare wide-area geofences - a whole campus, a city block) are tracked separately in
+/// `SpatialIndex::wide_radius_triggers` and checked directly on every query instead, which stays
+/// cheap precisely because outliers that large are rare; see `SpatialIndex::candidates_near`.
+const WIDE_RADIUS_THRESHOLD_METERS: f64 = 2_000.0;
+
+/// Encodes a (latitude, longitude) pair into a geohash by repeatedly bisecting the longitude
+/// range [-180, 180] and latitude range [-90, 90], alternating which one narrows on each bit,
+/// and base32-encoding the resulting bitstring.
+fn geohash_encode(latitude: f64, longitude: f64, precision: usize) -> String {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lng_range = (-180.0, 180.0);
+    let mut is_lng_bit = true;
+    let mut bit = 0u8;
+    let mut bits_in_char = 0;
+    let mut hash = String::with_capacity(precision);
+
+    while hash.len() < precision {
+        if is_lng_bit {
+            let mid = (lng_range.0 + lng_range.1) / 2.0;
+            if longitude >= mid {
+                bit = (bit << 1) | 1;
+                lng_range.0 = mid;
+            } else {
+                bit <<= 1;
+                lng_range.1 = mid;
+            }
+        } else {
+            let mid = (lat_range.0 + lat_range.1) / 2.0;
+            if latitude >= mid {
+                bit = (bit << 1) | 1;
+                lat_range.0 = mid;
+            } else {
+                bit <<= 1;
+                lat_range.1 = mid;
+            }
+        }
+        is_lng_bit = !is_lng_bit;
+
+        bits_in_char += 1;
+        if bits_in_char == 5 {
+            hash.push(GEOHASH_BASE32[bit as usize] as char);
+            bit = 0;
+            bits_in_char = 0;
+        }
+    }
+
+    hash
+}
+
+/// Decodes a geohash back to the (lat_min, lat_max, lng_min, lng_max) bounding box of its cell
+fn geohash_bounds(hash: &str) -> (f64, f64, f64, f64) {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lng_range = (-180.0, 180.0);
+    let mut is_lng_bit = true;
+
+    for c in hash.chars() {
+        let index = GEOHASH_BASE32.iter().position(|&b| b as char == c).unwrap_or(0);
+        for shift in (0..5).rev() {
+            let bit = (index >> shift) & 1;
+            if is_lng_bit {
+                let mid = (lng_range.0 + lng_range.1) / 2.0;
+                if bit == 1 {
+                    lng_range.0 = mid;
+                } else {
+                    lng_range.1 = mid;
+                }
+            } else {
+                let mid = (lat_range.0 + lat_range.1) / 2.0;
+                if bit == 1 {
+                    lat_range.0 = mid;
+                } else {
+                    lat_range.1 = mid;
+                }
+            }
+            is_lng_bit = !is_lng_bit;
+        }
+    }
+
+    (lat_range.0, lat_range.1, lng_range.0, lng_range.1)
+}
+
+/// Returns the geohash cells a query needs to check to find every trigger within
+/// `radius_meters` of (latitude, longitude): the point's own cell, its 8 neighbors, and (for
+/// radii that outgrow a single cell) as many additional rings as it takes to cover the radius.
+///
+/// Latitude and longitude rings are sized separately: a degree of longitude covers
+/// `cos(latitude)` fewer meters than a degree of latitude, shrinking towards the poles, so the
+/// same ring count in both directions would under-cover east/west at high latitudes. Each axis
+/// gets however many rings its own (in-meters) cell dimension requires.
+fn geohash_query_cells(latitude: f64, longitude: f64, radius_meters: f64, precision: usize) -> HashSet<String> {
+    let base = geohash_encode(latitude, longitude, precision);
+    let (lat_min, lat_max, lng_min, lng_max) = geohash_bounds(&base);
+    let lat_step = lat_max - lat_min;
+    let lng_step = lng_max - lng_min;
+    let cell_height_meters = lat_step * METERS_PER_LAT_DEGREE;
+    // cos(latitude), floored so a query point near a pole can't divide by ~0 and blow up the ring count
+    let lng_cos_factor = latitude.to_radians().cos().max(0.01);
+    let cell_width_meters = lng_step * METERS_PER_LAT_DEGREE * lng_cos_factor;
+
+    // How many cells wide a ring needs to be, per axis, so `radius_meters` can't spill past it.
+    // Always at least 1 so the classic 3x3 "point + 8 neighbors" block is covered. The `.min(50)`
+    // is a defensive bound against a pathologically large `radius_meters`, not something callers
+    // are expected to hit: `candidates_near` only ever calls this with
+    // `WIDE_RADIUS_THRESHOLD_METERS`, a few cells wide at this precision.
+    let lat_rings = ((radius_meters / cell_height_meters).ceil() as i64).max(1).min(50);
+    let lng_rings = ((radius_meters / cell_width_meters).ceil() as i64).max(1).min(50);
+
+    let mut cells = HashSet::new();
+    for dlat in -lat_rings..=lat_rings {
+        for dlng in -lng_rings..=lng_rings {
+            let neighbor_lat = (latitude + dlat as f64 * lat_step).clamp(-90.0, 90.0);
+            // Longitude wraps around the antimeridian instead of clamping
+            let mut neighbor_lng = longitude + dlng as f64 * lng_step;
+            while neighbor_lng > 180.0 {
+                neighbor_lng -= 360.0;
+            }
+            while neighbor_lng < -180.0 {
+                neighbor_lng += 360.0;
+            }
+            cells.insert(geohash_encode(neighbor_lat, neighbor_lng, precision));
+        }
+    }
+
+    cells
+}
+
+/// A single location trigger's position, as tracked by the spatial index
+#[derive(Debug, Clone)]
+struct IndexedTrigger {
+    todo_id: i64,
+    trigger_id: i64,
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    radius: f64,
+}
+
+/// Maps geohash cells to the triggers located in them, so `/todos/nearby` only has to run
+/// `calculate_distance` against the handful of triggers in nearby cells instead of every
+/// trigger of every todo. Rebuilt at startup and kept in sync on create/update/delete.
+#[derive(Default)]
+struct SpatialIndex {
+    // Only triggers with radius <= WIDE_RADIUS_THRESHOLD_METERS live here.
+    cells: HashMap<String, Vec<IndexedTrigger>>,
+    // Triggers with radius > WIDE_RADIUS_THRESHOLD_METERS - see the constant's doc comment.
+    wide_radius_triggers: Vec<IndexedTrigger>,
+    // Every indexed trigger regardless of bucket, keyed by trigger ID instead of cell, so the
+    // geofence state machine can look up a specific trigger a device was previously inside
+    // without a cell lookup.
+    by_trigger_id: HashMap<i64, IndexedTrigger>,
+}
+
+type SharedSpatialIndex = Arc<Mutex<SpatialIndex>>;
+
+impl SpatialIndex {
+    fn insert(&mut self, todo_id: i64, trigger: &LocationTrigger) {
+        let indexed = IndexedTrigger {
+            todo_id,
+            trigger_id: trigger.id as i64,
+            name: trigger.name.clone(),
+            latitude: trigger.latitude,
+            longitude: trigger.longitude,
+            radius: trigger.radius,
+        };
+        self.by_trigger_id.insert(indexed.trigger_id, indexed.clone());
+        if trigger.radius > WIDE_RADIUS_THRESHOLD_METERS {
+            self.wide_radius_triggers.push(indexed);
+        } else {
+            let cell = geohash_encode(trigger.latitude, trigger.longitude, GEOHASH_PRECISION);
+            self.cells.entry(cell).or_default().push(indexed);
+        }
+    }
+
+    /// Removes every indexed trigger belonging to a todo (used before re-indexing an update,
+    /// and when the todo itself is deleted)
+    fn remove_todo(&mut self, todo_id: i64) {
+        for entries in self.cells.values_mut() {
+            entries.retain(|entry| entry.todo_id != todo_id);
+        }
+        self.wide_radius_triggers.retain(|entry| entry.todo_id != todo_id);
+        self.by_trigger_id.retain(|_, entry| entry.todo_id != todo_id);
+    }
+
+    /// Finds every indexed trigger that might cover (latitude, longitude): the geohash cells
+    /// within `WIDE_RADIUS_THRESHOLD_METERS` cover every narrow-bucket trigger that could
+    /// possibly be in range (none of them has a radius big enough to reach further than that),
+    /// plus every wide-bucket trigger, checked unconditionally since there are few of them. The
+    /// caller still runs the exact Haversine check against whatever this returns - this only
+    /// narrows the candidate set.
+    fn candidates_near(&self, latitude: f64, longitude: f64, precision: usize) -> Vec<&IndexedTrigger> {
+        let cells = geohash_query_cells(latitude, longitude, WIDE_RADIUS_THRESHOLD_METERS, precision);
+        let mut candidates: Vec<&IndexedTrigger> = cells
+            .iter()
+            .filter_map(|cell| self.cells.get(cell))
+            .flatten()
+            .collect();
+        candidates.extend(self.wide_radius_triggers.iter());
+        candidates
+    }
+}
+
+// GEOFENCE STATE MACHINE
+// `/todos/nearby` used to be a stateless snapshot, so the client had to diff results itself to
+// notice the moment it *entered* a geofence (versus having been inside it all along). Instead,
+// the server now remembers which triggers each device was last inside and reports the
+// transition - entered, exited, or still dwelling - so the client can fire a notification
+// exactly when it should.
+
+/// The three geofence transitions a device can experience on a given trigger between reports
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GeofenceTransition {
+    Entered,  // Wasn't inside the trigger on the last report, is now
+    Exited,   // Was inside the trigger on the last report, isn't now
+    Dwelling, // Was inside on the last report, and still is
+}
+
+/// A single geofence transition, pushed to SSE subscribers and returned from `/todos/nearby`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GeofenceEvent {
+    pub device_id: String,
+    pub todo_id: u32,
+    pub trigger_id: u32,
+    pub trigger_name: String,
+    pub transition: GeofenceTransition,
+    pub distance_meters: f64,
+}
+
+/// Maps device_id -> (trigger_id -> distance_meters) for the triggers that device was inside
+/// as of its last location report, so the next report can be diffed against it to produce
+/// transitions. The distance is kept so an "exited" event can still report how far away the
+/// device was on the report that detected the exit.
+type DeviceGeofenceState = Arc<Mutex<HashMap<String, HashMap<i64, f64>>>>;
+
+/// Diffs a device's newly-computed "currently inside" trigger set against what it was
+/// previously inside, updates the stored state, and returns the resulting transitions.
+fn diff_geofence_state(
+    device_state: &DeviceGeofenceState,
+    index: &SpatialIndex,
+    device_id: &str,
+    currently_inside: HashMap<i64, f64>, // trigger_id -> distance_meters
+) -> Vec<GeofenceEvent> {
+    let mut devices = device_state.lock().unwrap();
+    let previously_inside = devices.remove(device_id).unwrap_or_default();
+
+    let mut events = Vec::new();
+
+    for (&trigger_id, &distance_meters) in &currently_inside {
+        let Some(trigger) = index.by_trigger_id.get(&trigger_id) else {
+            continue; // Trigger was deleted between reports; nothing to report on anymore
+        };
+        let transition = if previously_inside.contains_key(&trigger_id) {
+            GeofenceTransition::Dwelling
+        } else {
+            GeofenceTransition::Entered
+        };
+        events.push(GeofenceEvent {
+            device_id: device_id.to_string(),
+            todo_id: trigger.todo_id as u32,
+            trigger_id: trigger.trigger_id as u32,
+            trigger_name: trigger.name.clone(),
+            transition,
+            distance_meters,
+        });
+    }
+
+    for (&trigger_id, &last_distance_meters) in &previously_inside {
+        if currently_inside.contains_key(&trigger_id) {
+            continue; // Still inside - already reported as Dwelling above
+        }
+        let Some(trigger) = index.by_trigger_id.get(&trigger_id) else {
+            continue; // Trigger was deleted between reports
+        };
+        events.push(GeofenceEvent {
+            device_id: device_id.to_string(),
+            todo_id: trigger.todo_id as u32,
+            trigger_id: trigger.trigger_id as u32,
+            trigger_name: trigger.name.clone(),
+            transition: GeofenceTransition::Exited,
+            distance_meters: last_distance_meters,
+        });
+    }
+
+    devices.insert(device_id.to_string(), currently_inside);
+    events
+}
+
 // REST API ENDPOINT HANDLERS
 // Each function handles a specific HTTP endpoint and operation
 
-/// GET /todos - Returns all todos in the system
-/// iOS app can use this to sync all todos for offline access
-async fn get_todos(State(store): State<TodoStore>) -> Json<Vec<Todo>> {
-    let todos = store.lock().unwrap(); // Get exclusive access to the todo store
-    let todo_list: Vec<Todo> = todos.values().cloned().collect(); // Convert HashMap values to Vec
-    Json(todo_list) // Automatically serializes to JSON response
+/// Appends the `WHERE`/`AND` clauses for the filters set on `ListOptions` to a query builder.
+/// Shared between the count query and the page query in `get_todos` so they stay in sync.
+fn apply_list_filters<'a>(qb: &mut QueryBuilder<'a, Sqlite>, opts: &'a ListOptions) {
+    let mut has_condition = false;
+
+    if let Some(completed) = opts.completed {
+        qb.push(" WHERE completed = ").push_bind(completed);
+        has_condition = true;
+    }
+    if let Some(ref due_before) = opts.due_before {
+        qb.push(if has_condition { " AND " } else { " WHERE " });
+        qb.push("due_date <= ").push_bind(due_before);
+        has_condition = true;
+    }
+    if let Some(min_completion_percentage) = opts.min_completion_percentage {
+        qb.push(if has_condition { " AND " } else { " WHERE " });
+        qb.push("completion_percentage >= ").push_bind(min_completion_percentage as i64);
+    }
+}
+
+/// GET /todos - Returns a page of todos, optionally filtered
+/// iOS app can use this to sync todos for offline access, or to drive infinite scroll
+#[utoipa::path(
+    get,
+    path = "/todos",
+    params(ListOptions),
+    responses((status = 200, description = "A page of todos plus the total matching count", body = TodoPage)),
+    security(("api_key" = []))
+)]
+async fn get_todos(
+    Query(opts): Query<ListOptions>,
+    State(state): State<AppState>,
+) -> Result<Json<TodoPage>, StatusCode> {
+    let limit = opts.limit.unwrap_or(50).clamp(1, 200);
+    let offset = opts.offset.unwrap_or(0).max(0);
+
+    let mut count_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT COUNT(*) FROM todos");
+    apply_list_filters(&mut count_query, &opts);
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut page_query: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT id, title, completed, description, due_date, personal_notes, completion_percentage FROM todos",
+    );
+    apply_list_filters(&mut page_query, &opts);
+    page_query.push(" ORDER BY id LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+
+    let rows: Vec<TodoRow> = page_query
+        .build_query_as()
+        .fetch_all(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let todo_ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+    let mut triggers_by_todo = fetch_triggers_grouped(&state.pool, &todo_ids)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let todos: Vec<Todo> = rows
+        .into_iter()
+        .map(|row| {
+            let triggers = triggers_by_todo.remove(&row.id).unwrap_or_default();
+            row.into_todo(triggers)
+        })
+        .collect();
+
+    Ok(Json(TodoPage { total, todos })) // Automatically serializes to JSON response
+}
+
+/// GET /todos/search?q=... - Full-text search over title, description, and personal_notes
+/// Lets the iOS app offer a search bar instead of scrolling the whole list
+#[utoipa::path(
+    get,
+    path = "/todos/search",
+    params(SearchQuery),
+    responses((status = 200, description = "Todos matching the query", body = [Todo])),
+    security(("api_key" = []))
+)]
+async fn search_todos(
+    Query(search): Query<SearchQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Todo>>, StatusCode> {
+    let pattern = format!("%{}%", search.q);
+
+    let rows = sqlx::query_as::<_, TodoRow>(
+        "SELECT id, title, completed, description, due_date, personal_notes, completion_percentage
+         FROM todos
+         WHERE title LIKE ? OR description LIKE ? OR personal_notes LIKE ?
+         ORDER BY id",
+    )
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let todo_ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+    let mut triggers_by_todo = fetch_triggers_grouped(&state.pool, &todo_ids)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let matches: Vec<Todo> = rows
+        .into_iter()
+        .map(|row| {
+            let triggers = triggers_by_todo.remove(&row.id).unwrap_or_default();
+            row.into_todo(triggers)
+        })
+        .collect();
+
+    Ok(Json(matches))
+}
+
+/// PATCH /todos/{id}/done - Marks a todo complete (or incomplete) without resending its full body
+#[utoipa::path(
+    patch,
+    path = "/todos/{id}/done",
+    params(("id" = u32, Path, description = "Todo ID")),
+    request_body = MarkDone,
+    responses(
+        (status = 200, description = "The updated todo", body = Todo),
+        (status = 404, description = "No todo with that ID"),
+    ),
+    security(("api_key" = []))
+)]
+async fn mark_done(
+    Path(id): Path<u32>,
+    State(state): State<AppState>,
+    Json(payload): Json<MarkDone>,
+) -> Result<Json<Todo>, StatusCode> {
+    let result = sqlx::query("UPDATE todos SET completed = ? WHERE id = ?")
+        .bind(payload.completed)
+        .bind(id as i64)
+        .execute(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let row = sqlx::query_as::<_, TodoRow>(
+        "SELECT id, title, completed, description, due_date, personal_notes, completion_percentage FROM todos WHERE id = ?",
+    )
+    .bind(id as i64)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let triggers = fetch_triggers(&state.pool, row.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(row.into_todo(triggers)))
 }
 
 /// GET /todos/{id} - Returns a specific todo by ID
 /// Useful for getting detailed information about a single todo
+#[utoipa::path(
+    get,
+    path = "/todos/{id}",
+    params(("id" = u32, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "The matching todo", body = Todo),
+        (status = 404, description = "No todo with that ID"),
+    ),
+    security(("api_key" = []))
+)]
 async fn get_todo(
     Path(id): Path<u32>,           // Extract ID from URL path
-    State(store): State<TodoStore>, // Get access to shared todo storage
+    State(state): State<AppState>, // Get access to the database pool
 ) -> Result<Json<Todo>, StatusCode> {
-    let todos = store.lock().unwrap();
-    match todos.get(&id) {
-        Some(todo) => Ok(Json(todo.clone())), // Found: return the todo
-        None => Err(StatusCode::NOT_FOUND),   // Not found: return 404
+    let row = sqlx::query_as::<_, TodoRow>(
+        "SELECT id, title, completed, description, due_date, personal_notes, completion_percentage FROM todos WHERE id = ?",
+    )
+    .bind(id as i64)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match row {
+        Some(row) => {
+            let triggers = fetch_triggers(&state.pool, row.id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            Ok(Json(row.into_todo(triggers))) // Found: return the todo
+        }
+        None => Err(StatusCode::NOT_FOUND), // Not found: return 404
     }
 }
 
 /// POST /todos - Creates a new todo
 /// iOS app uses this to add new todos with location triggers
+///
+/// If `address` is set on the payload, it's geocoded into an extra `LocationTrigger`
+/// (via `state.geocoder`, cached by normalized address in `state.geocode_cache`) so the
+/// iOS app never has to handle raw coordinates itself.
+#[utoipa::path(
+    post,
+    path = "/todos",
+    request_body = CreateTodo,
+    responses((status = 200, description = "The created todo", body = Todo)),
+    security(("api_key" = []))
+)]
 async fn create_todo(
-    State(store): State<TodoStore>,    // Access to todo storage
+    State(state): State<AppState>,     // Access to the database pool, geocoder, and cache
     Json(payload): Json<CreateTodo>,   // Extract JSON payload from request body
-) -> Json<Todo> {
-    // Create new todo with generated ID and default values
-    let todo = Todo {
-        id: get_next_id(),                      // Generate unique ID
-        title: payload.title,                   // Use provided title
-        completed: false,                       // New todos start incomplete
-        description: payload.description,       // Optional description
-        due_date: payload.due_date,            // Optional due date
-        personal_notes: payload.personal_notes, // Optional notes
-        completion_percentage: payload.completion_percentage, // Optional progress
-        location_triggers: payload.location_triggers, // Optional location triggers
-    };
+) -> Result<Json<Todo>, StatusCode> {
+    let mut pending_triggers = payload.location_triggers.unwrap_or_default();
 
-    // Store the new todo and return it
-    let mut todos = store.lock().unwrap();
-    todos.insert(todo.id, todo.clone());
-    Json(todo) // Return the created todo with its assigned ID
+    if let Some(address) = payload.address {
+        let resolved = resolve_address(&state, &address).await?;
+        pending_triggers.push(LocationTrigger {
+            id: 0, // Placeholder: the real ID comes from the DB once inserted below
+            name: address,
+            latitude: resolved.latitude,
+            longitude: resolved.longitude,
+            radius: 150.0, // Default geofence radius for a geocoded street address
+        });
+    }
+
+    // Insert the todo first so we have an ID to attach triggers to
+    let row: TodoRow = sqlx::query_as(
+        "INSERT INTO todos (title, completed, description, due_date, personal_notes, completion_percentage)
+         VALUES (?, FALSE, ?, ?, ?, ?)
+         RETURNING id, title, completed, description, due_date, personal_notes, completion_percentage",
+    )
+    .bind(&payload.title)
+    .bind(&payload.description)
+    .bind(&payload.due_date)
+    .bind(&payload.personal_notes)
+    .bind(payload.completion_percentage.map(|p| p as i64))
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut saved_triggers = Vec::with_capacity(pending_triggers.len());
+    for trigger in &pending_triggers {
+        let saved = insert_trigger(&state.pool, row.id, trigger)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        state.spatial_index.lock().unwrap().insert(row.id, &saved);
+        saved_triggers.push(saved);
+    }
+
+    Ok(Json(row.into_todo(saved_triggers))) // Return the created todo with its assigned ID
+}
+
+/// POST /geocode - Resolves a free-text address to coordinates without creating a todo
+/// Lets the iOS app preview/confirm a location before attaching it to anything.
+#[utoipa::path(
+    post,
+    path = "/geocode",
+    request_body = GeocodeRequest,
+    responses(
+        (status = 200, description = "Resolved coordinates", body = GeocodeResponse),
+        (status = 422, description = "Address could not be resolved"),
+        (status = 502, description = "Upstream geocoding service failed"),
+    ),
+    security(("api_key" = []))
+)]
+async fn geocode_address(
+    State(state): State<AppState>,
+    Json(payload): Json<GeocodeRequest>,
+) -> Result<Json<GeocodeResponse>, StatusCode> {
+    let resolved = resolve_address(&state, &payload.address).await?;
+    Ok(Json(resolved))
+}
+
+/// Shared address -> coordinates resolution used by both `create_todo` and `geocode_address`.
+/// Checks the cache first, then falls back to the configured `GeocoderClient`.
+async fn resolve_address(state: &AppState, address: &str) -> Result<GeocodeResponse, StatusCode> {
+    let normalized = normalize_address(address);
+
+    if let Some(cached) = state.geocode_cache.lock().unwrap().get(&normalized) {
+        return Ok(cached.clone());
+    }
+
+    let resolved = state.geocoder.geocode(address).await.map_err(|e| match e {
+        GeocodeError::NotFound => StatusCode::UNPROCESSABLE_ENTITY, // Address couldn't be resolved
+        GeocodeError::ServiceError(_) => StatusCode::BAD_GATEWAY,   // Upstream geocoder failed
+    })?;
+
+    state
+        .geocode_cache
+        .lock()
+        .unwrap()
+        .insert(normalized, resolved.clone());
+
+    Ok(resolved)
 }
 
 /// PUT /todos/{id} - Updates an existing todo
 /// iOS app uses this to mark todos complete, update progress, etc.
+#[utoipa::path(
+    put,
+    path = "/todos/{id}",
+    params(("id" = u32, Path, description = "Todo ID")),
+    request_body = Todo,
+    responses(
+        (status = 200, description = "The updated todo", body = Todo),
+        (status = 404, description = "No todo with that ID"),
+    ),
+    security(("api_key" = []))
+)]
 async fn update_todo(
     Path(id): Path<u32>,               // Todo ID from URL
-    State(store): State<TodoStore>,    // Access to storage
+    State(state): State<AppState>,     // Access to the database pool
     Json(payload): Json<Todo>,         // New todo data from request body
 ) -> Result<Json<Todo>, StatusCode> {
-    let mut todos = store.lock().unwrap();
-    if todos.contains_key(&id) {
-        todos.insert(id, payload.clone()); // Replace existing todo
-        Ok(Json(payload))                  // Return updated todo
-    } else {
-        Err(StatusCode::NOT_FOUND)         // Todo doesn't exist
+    let mut tx = state.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let result = sqlx::query(
+        "UPDATE todos SET title = ?, completed = ?, description = ?, due_date = ?, personal_notes = ?, completion_percentage = ? WHERE id = ?",
+    )
+    .bind(&payload.title)
+    .bind(payload.completed)
+    .bind(&payload.description)
+    .bind(&payload.due_date)
+    .bind(&payload.personal_notes)
+    .bind(payload.completion_percentage.map(|p| p as i64))
+    .bind(id as i64)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND); // Todo doesn't exist
+    }
+
+    // The payload carries the full desired set of triggers, so replace them wholesale
+    // rather than trying to diff against what's already stored.
+    sqlx::query("DELETE FROM location_triggers WHERE todo_id = ?")
+        .bind(id as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut saved_triggers = Vec::new();
+    for trigger in payload.location_triggers.iter().flatten() {
+        let row: LocationTriggerRow = sqlx::query_as(
+            "INSERT INTO location_triggers (todo_id, name, latitude, longitude, radius)
+             VALUES (?, ?, ?, ?, ?)
+             RETURNING id, todo_id, name, latitude, longitude, radius",
+        )
+        .bind(id as i64)
+        .bind(&trigger.name)
+        .bind(trigger.latitude)
+        .bind(trigger.longitude)
+        .bind(trigger.radius)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        saved_triggers.push(row.into());
     }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Re-index: drop this todo's old entries (if any) and insert the ones we just saved
+    {
+        let mut index = state.spatial_index.lock().unwrap();
+        index.remove_todo(id as i64);
+        for trigger in &saved_triggers {
+            index.insert(id as i64, trigger);
+        }
+    }
+
+    Ok(Json(Todo {
+        location_triggers: if saved_triggers.is_empty() { None } else { Some(saved_triggers) },
+        ..payload
+    })) // Return updated todo
 }
 
 /// DELETE /todos/{id} - Removes a todo
 /// iOS app can use this to delete completed or unwanted todos
+#[utoipa::path(
+    delete,
+    path = "/todos/{id}",
+    params(("id" = u32, Path, description = "Todo ID")),
+    responses(
+        (status = 204, description = "Todo deleted"),
+        (status = 404, description = "No todo with that ID"),
+    ),
+    security(("api_key" = []))
+)]
 async fn delete_todo(
     Path(id): Path<u32>,            // Todo ID to delete
-    State(store): State<TodoStore>, // Access to storage
+    State(state): State<AppState>,  // Access to the database pool
 ) -> Result<StatusCode, StatusCode> {
-    let mut todos = store.lock().unwrap();
-    if todos.remove(&id).is_some() {
+    // `location_triggers.todo_id` has `ON DELETE CASCADE`, so deleting the todo row
+    // removes its triggers too.
+    let result = sqlx::query("DELETE FROM todos WHERE id = ?")
+        .bind(id as i64)
+        .execute(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() > 0 {
+        // The DB cascade doesn't reach into our in-memory spatial index
+        state.spatial_index.lock().unwrap().remove_todo(id as i64);
         Ok(StatusCode::NO_CONTENT)  // Successfully deleted (204)
     } else {
         Err(StatusCode::NOT_FOUND)  // Todo didn't exist (404)
     }
 }
 
-/// GET /todos/nearby?lat=37.7749&lng=-122.4194
+/// GET /todos/nearby?lat=37.7749&lng=-122.4194&device_id=iphone-123
 /// CRITICAL ENDPOINT: This is the core of your location-based notification system
-/// 
-/// When the iOS app detects a location change, it calls this endpoint with the
-/// current GPS coordinates. The server responds with todos that have location
-/// triggers within range of the current position.
-/// 
-/// The iOS app can then display local notifications for these nearby todos.
+///
+/// When the iOS app detects a location change, it calls this endpoint with the current GPS
+/// coordinates and its device ID. Rather than a flat snapshot of nearby todos, the server
+/// diffs the triggers this device is now inside against what it was inside on its last report
+/// (see `diff_geofence_state`) and returns the resulting `entered`/`exited`/`dwelling` events -
+/// each of which is also pushed to `/todos/nearby/stream` for any connected SSE subscriber.
+#[utoipa::path(
+    get,
+    path = "/todos/nearby",
+    params(LocationQuery),
+    responses((status = 200, description = "Geofence transitions since this device's last report", body = [GeofenceEvent])),
+    security(("api_key" = []))
+)]
 async fn get_nearby_todos(
-    Query(location): Query<LocationQuery>, // Extract lat/lng from query parameters
-    State(store): State<TodoStore>,        // Access to todo storage
-) -> Json<Vec<Todo>> {
-    let todos = store.lock().unwrap();
-    
-    // Filter todos to find those with location triggers near the current position
-    let nearby_todos: Vec<Todo> = todos
-        .values()
-        .filter(|todo| {
-            // Only check todos that have location triggers defined
-            if let Some(ref triggers) = todo.location_triggers {
-                // Check if ANY trigger is within range of current location
-                triggers.iter().any(|trigger| {
-                    // Calculate distance between current location and trigger
-                    let distance = calculate_distance(
-                        location.lat,      // Current latitude from iOS
-                        location.lng,      // Current longitude from iOS
-                        trigger.latitude,  // Trigger's latitude
-                        trigger.longitude, // Trigger's longitude
-                    );
-                    
-                    // Todo is "nearby" if:
-                    // 1. Distance is within the trigger's radius AND
-                    // 2. Todo is not already completed
-                    distance <= trigger.radius && !todo.completed
-                })
-            } else {
-                false // No location triggers = not location-based
-            }
-        })
-        .cloned() // Create owned copies of the todos
+    Query(location): Query<LocationQuery>, // Extract lat/lng/device_id from query parameters
+    State(state): State<AppState>,         // Access to the database pool + spatial index
+) -> Result<Json<Vec<GeofenceEvent>>, StatusCode> {
+    // Narrow down to the handful of triggers in cells near the query point, then run the
+    // exact Haversine check only on those - not every trigger of every todo.
+    let candidates: Vec<(i64, i64, f64)> = {
+        // (todo_id, trigger_id, distance_meters)
+        let index = state.spatial_index.lock().unwrap();
+
+        index
+            .candidates_near(location.lat, location.lng, GEOHASH_PRECISION)
+            .into_iter()
+            .filter_map(|trigger| {
+                let distance = calculate_distance(location.lat, location.lng, trigger.latitude, trigger.longitude);
+                (distance <= trigger.radius).then_some((trigger.todo_id, trigger.trigger_id, distance))
+            })
+            .collect()
+    };
+
+    // Triggers on completed todos shouldn't fire geofence events
+    let candidate_todo_ids: HashSet<i64> = candidates.iter().map(|(todo_id, _, _)| *todo_id).collect();
+    let completed_todo_ids: HashSet<i64> = if candidate_todo_ids.is_empty() {
+        HashSet::new()
+    } else {
+        let mut completed_query: QueryBuilder<Sqlite> =
+            QueryBuilder::new("SELECT id FROM todos WHERE completed = TRUE AND id IN (");
+        let mut separated = completed_query.separated(", ");
+        for todo_id in &candidate_todo_ids {
+            separated.push_bind(todo_id);
+        }
+        completed_query.push(")");
+        completed_query
+            .build_query_scalar()
+            .fetch_all(&state.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .collect()
+    };
+
+    let currently_inside: HashMap<i64, f64> = candidates
+        .into_iter()
+        .filter(|(todo_id, _, _)| !completed_todo_ids.contains(todo_id))
+        .map(|(_, trigger_id, distance)| (trigger_id, distance))
         .collect();
 
-    Json(nearby_todos) // Return nearby todos as JSON
+    let events = {
+        let index = state.spatial_index.lock().unwrap();
+        diff_geofence_state(&state.device_geofence_state, &index, &location.device_id, currently_inside)
+    };
+
+    for event in &events {
+        let _ = state.geofence_events.send(event.clone()); // Ok to drop if nobody's subscribed
+    }
+
+    Ok(Json(events))
+}
+
+/// GET /todos/nearby/stream?device_id=... - Server-sent events of geofence transitions
+/// Lets a backgrounded client receive `entered`/`exited` events pushed from the server as soon
+/// as any `/todos/nearby` report produces them, instead of polling.
+#[utoipa::path(
+    get,
+    path = "/todos/nearby/stream",
+    params(("device_id" = String, Query, description = "Only stream transitions for this device")),
+    responses((status = 200, description = "text/event-stream of GeofenceEvent payloads")),
+    security(("api_key" = []))
+)]
+async fn geofence_event_stream(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let device_id = params.get("device_id").cloned();
+    let receiver = state.geofence_events.subscribe();
+
+    let stream = BroadcastStream::new(receiver).filter_map(move |result| {
+        let device_id = device_id.clone();
+        async move {
+            let event = result.ok()?; // Drop messages missed due to a slow/lagging subscriber
+            if device_id.as_deref().is_some_and(|id| id != event.device_id) {
+                return None; // Not this subscriber's device
+            }
+            let payload = serde_json::to_string(&event).ok()?;
+            Some(Ok(Event::default().event(format!("{:?}", event.transition).to_lowercase()).data(payload)))
+        }
+    });
+
+    Sse::new(stream)
+}
+
+// API DOCUMENTATION
+// `utoipa` builds the OpenAPI spec straight from the `#[utoipa::path(...)]` annotations and
+// `ToSchema` derives above, and `utoipa-swagger-ui` serves it as an interactive UI. This gives
+// the iOS client (and any future web frontend) a standard contract to generate models from.
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_todos,
+        get_todo,
+        create_todo,
+        update_todo,
+        delete_todo,
+        get_nearby_todos,
+        geofence_event_stream,
+        geocode_address,
+        search_todos,
+        mark_done,
+    ),
+    components(schemas(
+        Todo, LocationTrigger, CreateTodo, LocationQuery, GeocodeRequest, GeocodeResponse,
+        ListOptions, TodoPage, SearchQuery, MarkDone, GeofenceEvent, GeofenceTransition,
+    )),
+    modifiers(&SecurityAddon),
+    tags((name = "todos", description = "Location-aware todo API"))
+)]
+struct ApiDoc;
+
+/// Registers the `api_key` security scheme (checked via an `x-api-key` header) so Swagger UI
+/// can prompt for it and deployments can require it on the todo endpoints.
+struct SecurityAddon;
+
+impl utoipa::Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+            );
+        }
+    }
+}
+
+// API-KEY AUTH MIDDLEWARE
+// A pluggable security scheme: when `API_KEY` is set in the environment, every todo endpoint
+// requires a matching `x-api-key` header. Leaving it unset disables auth, which keeps local
+// development (and Swagger UI's "Try it out") frictionless.
+
+/// Rejects requests missing or mismatching the configured `x-api-key` header
+async fn require_api_key(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(expected) = &state.api_key else {
+        return next.run(req).await; // Auth disabled: pass everything through
+    };
+
+    let provided = req
+        .headers()
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok());
+
+    if provided == Some(expected.as_str()) {
+        next.run(req).await
+    } else {
+        StatusCode::UNAUTHORIZED.into_response()
+    }
 }
 
 // MAIN APPLICATION SETUP
 
 #[tokio::main] // Enables async main function with tokio runtime
 async fn main() {
-    // Initialize shared application state (todo storage)
-    let store: TodoStore = Arc::new(Mutex::new(HashMap::new()));
+    // Connect to the SQLite database (todos.db in the working directory; override with DATABASE_URL)
+    let database_url =
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://todos.db".to_string());
+    let connect_options = SqliteConnectOptions::from_str(&database_url)
+        .expect("invalid DATABASE_URL")
+        .create_if_missing(true)
+        .foreign_keys(true); // Needed for `ON DELETE CASCADE` on location_triggers to fire
 
-    // ADD SAMPLE DATA FOR TESTING
-    // In production, this would be loaded from a database
-    {
-        let mut todos = store.lock().unwrap();
-        let sample_todo = Todo {
-            id: 1,
-            title: String::from("Finish Rust project"),
-            completed: false,
-            description: Some(String::from("Complete the Rust project for the client.")),
-            due_date: Some(String::from("2024-07-01")),
-            personal_notes: Some(String::from("Have completed the initial setup and basic functionality.")),
-            completion_percentage: Some(50),
-            location_triggers: Some(vec![
-                LocationTrigger {
-                    id: 1,
-                    name: String::from("Home"),
-                    latitude: 37.7749,    // San Francisco coordinates
-                    longitude: -122.4194,
-                    radius: 100.0,        // 100 meter radius
-                },
-            ]),
-        };
-        todos.insert(1, sample_todo);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(connect_options)
+        .await
+        .expect("failed to connect to SQLite database");
+
+    // Apply migrations (see migrations/0001_init.sql) so `todos` and `location_triggers`
+    // exist on a fresh database, and are left alone if they're already up to date.
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("failed to run database migrations");
+
+    // Build the geohash spatial index from whatever triggers are already in the database
+    let mut spatial_index = SpatialIndex::default();
+    let existing_triggers = sqlx::query_as::<_, LocationTriggerRow>(
+        "SELECT id, todo_id, name, latitude, longitude, radius FROM location_triggers",
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("failed to load location triggers for spatial index");
+    for row in existing_triggers {
+        let todo_id = row.todo_id;
+        spatial_index.insert(todo_id, &row.into());
     }
 
+    let state = AppState {
+        pool,
+        geocoder: Arc::new(NominatimGeocoder::new()),
+        geocode_cache: Arc::new(Mutex::new(HashMap::new())),
+        api_key: std::env::var("API_KEY").ok().map(Arc::new),
+        spatial_index: Arc::new(Mutex::new(spatial_index)),
+        device_geofence_state: Arc::new(Mutex::new(HashMap::new())),
+        geofence_events: broadcast::channel(100).0,
+    };
+
     // BUILD THE API ROUTER
     // This defines all available endpoints and their HTTP methods
     let app = Router::new()
         // Todo CRUD operations
         .route("/todos", get(get_todos).post(create_todo))              // GET /todos, POST /todos
         .route("/todos/:id", get(get_todo).put(update_todo).delete(delete_todo)) // GET/PUT/DELETE /todos/{id}
-        
+        .route("/todos/:id/done", patch(mark_done))                     // PATCH /todos/{id}/done
+
+        // Full-text search across title/description/personal_notes
+        .route("/todos/search", get(search_todos))                     // GET /todos/search?q=...
+
         // Location-based endpoint (MOST IMPORTANT for iOS integration)
         .route("/todos/nearby", get(get_nearby_todos))                 // GET /todos/nearby?lat=X&lng=Y
-        
+        .route("/todos/nearby/stream", get(geofence_event_stream))     // GET /todos/nearby/stream (SSE)
+
+        // Address -> coordinates lookup, so the client never has to handle raw GPS coordinates
+        .route("/geocode", post(geocode_address))                      // POST /geocode
+
+        // Require a matching x-api-key header once API_KEY is configured (see require_api_key)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_api_key))
+
+        // Swagger UI + the raw OpenAPI spec it reads from, left outside the auth layer so the
+        // docs stay browsable even when the todo endpoints are locked down
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+
         // Enable CORS for web browser access (if building a web interface later)
         .layer(CorsLayer::permissive())
-        
+
         // Inject shared state into all handlers
-        .with_state(store);
+        .with_state(state);
 
     // START THE SERVER
     // Bind to all network interfaces (0.0.0.0) so iOS devices on the same network can connect
@@ -291,11 +1357,120 @@ async fn main() {
     println!("ðŸš€ Todo Location Server running on http://0.0.0.0:3000");
     println!("ðŸ“± iOS app can connect to: http://[YOUR_COMPUTER_IP]:3000");
     println!("ðŸ” Test nearby todos: http://localhost:3000/todos/nearby?lat=37.7749&lng=-122.4194");
-    
+    println!("ðŸ“š API docs (Swagger UI): http://localhost:3000/swagger-ui");
+
     // Run the server indefinitely
     axum::serve(listener, app).await.unwrap();
 }
 
+// Coverage for the geospatial index and the geofence state machine - the two pieces of this
+// file with real logic in them (bit-twiddling geohash encode/decode, radius-tiered candidate
+// search, and a stateful diff), as opposed to straight-line handler/query code.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geohash_bounds_contains_the_point_it_was_encoded_from() {
+        let (lat, lng) = (37.7749, -122.4194);
+        let hash = geohash_encode(lat, lng, GEOHASH_PRECISION);
+        let (lat_min, lat_max, lng_min, lng_max) = geohash_bounds(&hash);
+
+        assert!(lat_min <= lat && lat <= lat_max);
+        assert!(lng_min <= lng && lng <= lng_max);
+    }
+
+    #[test]
+    fn geohash_bounds_shrinks_as_precision_increases() {
+        let (lat, lng) = (51.5074, -0.1278);
+        let coarse = geohash_bounds(&geohash_encode(lat, lng, 3));
+        let fine = geohash_bounds(&geohash_encode(lat, lng, 8));
+
+        let coarse_width = coarse.3 - coarse.2;
+        let fine_width = fine.3 - fine.2;
+        assert!(fine_width < coarse_width);
+    }
+
+    #[test]
+    fn candidates_near_finds_a_distant_large_radius_trigger() {
+        // Regression test for the bug fixed alongside this test: a 100km-radius trigger 55.6km
+        // away from the query point is well within range, but sits many geohash cells outside
+        // any ring sized for an ordinary small geofence.
+        let mut index = SpatialIndex::default();
+        index.insert(
+            1,
+            &LocationTrigger {
+                id: 1,
+                name: "Wide-area geofence".to_string(),
+                latitude: 0.5,
+                longitude: 0.0,
+                radius: 100_000.0,
+            },
+        );
+
+        let candidates = index.candidates_near(0.0, 0.0, GEOHASH_PRECISION);
+        let found = candidates.iter().any(|trigger| {
+            trigger.trigger_id == 1
+                && calculate_distance(0.0, 0.0, trigger.latitude, trigger.longitude) <= trigger.radius
+        });
+        assert!(found, "candidates_near should not drop an in-range wide-radius trigger");
+    }
+
+    #[test]
+    fn candidates_near_finds_an_ordinary_nearby_trigger() {
+        let mut index = SpatialIndex::default();
+        index.insert(
+            1,
+            &LocationTrigger {
+                id: 1,
+                name: "Office".to_string(),
+                latitude: 0.001,
+                longitude: 0.001,
+                radius: 150.0,
+            },
+        );
+
+        let candidates = index.candidates_near(0.0, 0.0, GEOHASH_PRECISION);
+        assert!(candidates.iter().any(|trigger| trigger.trigger_id == 1));
+    }
+
+    #[test]
+    fn diff_geofence_state_walks_entered_dwelling_exited() {
+        let device_state: DeviceGeofenceState = Arc::new(Mutex::new(HashMap::new()));
+        let mut index = SpatialIndex::default();
+        index.insert(
+            1,
+            &LocationTrigger {
+                id: 1,
+                name: "Home".to_string(),
+                latitude: 0.0,
+                longitude: 0.0,
+                radius: 150.0,
+            },
+        );
+
+        // Not inside on the first report -> no events.
+        let events = diff_geofence_state(&device_state, &index, "device-1", HashMap::new());
+        assert!(events.is_empty());
+
+        // Now inside -> Entered.
+        let events = diff_geofence_state(&device_state, &index, "device-1", HashMap::from([(1, 42.0)]));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].transition, GeofenceTransition::Entered));
+
+        // Still inside on the next report -> Dwelling.
+        let events = diff_geofence_state(&device_state, &index, "device-1", HashMap::from([(1, 10.0)]));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].transition, GeofenceTransition::Dwelling));
+
+        // No longer inside -> Exited, reporting the last known distance.
+        let events = diff_geofence_state(&device_state, &index, "device-1", HashMap::new());
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].transition, GeofenceTransition::Exited));
+        assert_eq!(events[0].distance_meters, 10.0);
+    }
+}
+
 // IMPLEMENTATION ROADMAP FOR YOUR iOS APP:
 //
 // 1. LOCATION MONITORING: